@@ -0,0 +1,229 @@
+//! ## Downgrading: writing old wire versions from new in-memory structs
+//!
+//! [`FromVersion`](crate::FromVersion) and
+//! [`UpgradeLatest`](crate::versioned::UpgradeLatest) only ever go one
+//! direction: an old struct, read off the wire, is upgraded to the
+//! latest version in memory. That's enough for a reader that only cares
+//! about "give me the newest shape of this message", but it doesn't help
+//! a *writer* during a rolling upgrade, when a newer peer may need to
+//! keep writing the old wire format for the benefit of older peers that
+//! haven't upgraded yet.
+//!
+//! This module adds the mirror image: [`ToVersion`] walks the version
+//! chain downward, converting the latest in-memory struct into an older
+//! one, field by field:
+//! ```
+//! # use aversion::{FromVersion, Versioned};
+//! use aversion::downgrade::ToVersion;
+//!
+//! # #[derive(Versioned)]
+//! # struct FooV1 { val: u32 }
+//! # #[derive(Versioned)]
+//! # struct FooV2 { val: u64 }
+//! # impl FromVersion<FooV1> for FooV2 {
+//! #     fn from_version(v1: FooV1) -> Self { FooV2 { val: v1.val.into() } }
+//! # }
+//! impl ToVersion<FooV1> for FooV2 {
+//!     fn to_version(self) -> FooV1 {
+//!         FooV1 { val: self.val as u32 }
+//!     }
+//! }
+//! ```
+//! Note that, unlike `FromVersion`, a downgrade can be lossy (here,
+//! truncating a `u64` back down to a `u32`); that's an accepted tradeoff
+//! of choosing to write an older wire format.
+//!
+//! [`DowngradeLatest`] builds on `ToVersion` the same way
+//! `UpgradeLatest` builds on `FromVersion`: given the latest struct and
+//! a target wire version, it walks the chain down from the latest
+//! version to the target, then serializes the result with the correct
+//! header (`MSG_ID` plus the chosen struct version):
+//! ```text
+//! let msg: Foo = ...; // the latest version, as used everywhere else
+//! msg.downgrade_to(sink, target_ver)?;
+//! ```
+//! As a counterpart to [`GroupDeserialize`](crate::GroupDeserialize),
+//! the [`GroupSerialize`] trait covers enums of message types: its
+//! `write_message` dispatches to the active variant's `downgrade_to`.
+//! This crate provides no derive macro for `DowngradeLatest` or
+//! `GroupSerialize`; both are implemented by hand. To write a protocol
+//! out at a chosen [`VersionMap`](crate::VersionMap) application
+//! version, the caller looks up each message family's target version in
+//! the map.
+
+use crate::Versioned;
+
+/// Convert `Self` (the latest version of a struct) down into an earlier
+/// version `T`.
+///
+/// This is the mirror image of
+/// [`FromVersion`](crate::FromVersion): instead of upgrading an old
+/// struct on read, it downgrades the latest struct for a write. Downgrades
+/// form a chain, e.g. `FooV3: ToVersion<FooV2>`, `FooV2: ToVersion<FooV1>`,
+/// and [`DowngradeLatest::downgrade_to`] walks that chain down to
+/// whichever target version is requested.
+pub trait ToVersion<T> {
+    /// Downgrade `self` into the older representation `T`.
+    fn to_version(self) -> T;
+}
+
+/// Serialize the latest version of a struct as an arbitrary, possibly
+/// older, wire version.
+///
+/// An implementation walks the [`ToVersion`] chain down from `Self` to
+/// the struct version matching `target_ver`, then serializes that struct
+/// together with a header identifying
+/// [`MSG_ID`](crate::MessageId::MSG_ID) and the chosen version. There is
+/// no derive macro for this trait; it is implemented by hand.
+pub trait DowngradeLatest: Versioned + Sized {
+    /// The error type returned when `target_ver` isn't a version this
+    /// type ever had, or the underlying sink fails.
+    type Error;
+
+    /// Downgrade `self` to `target_ver` and write it to `sink`.
+    ///
+    /// `target_ver` must be between `1` and `Self::VER` inclusive;
+    /// anything outside that range is a [`DowngradeLatest::Error`].
+    fn downgrade_to<W>(self, sink: W, target_ver: u16) -> Result<(), Self::Error>
+    where
+        W: std::io::Write;
+}
+
+/// Write whichever message variant `self` holds, downgraded to a chosen
+/// wire version.
+///
+/// This is the write-side counterpart of
+/// [`GroupDeserialize`](crate::GroupDeserialize): for an enum of message
+/// types, an impl matches on the active variant and calls its
+/// [`DowngradeLatest::downgrade_to`] with `target_ver`. There is no
+/// derive macro for this trait; it is implemented by hand. Callers that want every message family downgraded
+/// consistently for a single [`VersionMap`](crate::VersionMap)
+/// application version look up each family's `target_ver` from the map
+/// themselves before calling `write_message`.
+pub trait GroupSerialize {
+    /// The error type shared by every message variant's downgrade path.
+    type Error;
+
+    /// Write the active message, downgraded to `target_ver` for its
+    /// message family.
+    fn write_message<W>(self, sink: W, target_ver: u16) -> Result<(), Self::Error>
+    where
+        W: std::io::Write;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Versioned;
+    use std::io::{self, Write};
+
+    // A hand-written 3-version chain with a hand-written
+    // `DowngradeLatest` impl: each version drops the field its successor
+    // added. The impl writes only the payload bytes, without a header.
+    struct FooV1 {
+        val: u32,
+    }
+    struct FooV2 {
+        val: u32,
+        extra: u16,
+    }
+    struct FooV3 {
+        val: u32,
+        extra: u16,
+        flag: u8,
+    }
+
+    impl Versioned for FooV1 {
+        const VER: u16 = 1;
+    }
+    impl Versioned for FooV2 {
+        const VER: u16 = 2;
+    }
+    impl Versioned for FooV3 {
+        const VER: u16 = 3;
+    }
+
+    impl ToVersion<FooV1> for FooV2 {
+        fn to_version(self) -> FooV1 {
+            FooV1 { val: self.val }
+        }
+    }
+    impl ToVersion<FooV2> for FooV3 {
+        fn to_version(self) -> FooV2 {
+            FooV2 {
+                val: self.val,
+                extra: self.extra,
+            }
+        }
+    }
+
+    fn write_v1<W: Write>(v: &FooV1, mut sink: W) -> io::Result<()> {
+        sink.write_all(&v.val.to_le_bytes())
+    }
+    fn write_v2<W: Write>(v: &FooV2, mut sink: W) -> io::Result<()> {
+        sink.write_all(&v.val.to_le_bytes())?;
+        sink.write_all(&v.extra.to_le_bytes())
+    }
+    fn write_v3<W: Write>(v: &FooV3, mut sink: W) -> io::Result<()> {
+        sink.write_all(&v.val.to_le_bytes())?;
+        sink.write_all(&v.extra.to_le_bytes())?;
+        sink.write_all(&[v.flag])
+    }
+
+    impl DowngradeLatest for FooV3 {
+        type Error = io::Error;
+
+        fn downgrade_to<W>(self, sink: W, target_ver: u16) -> io::Result<()>
+        where
+            W: Write,
+        {
+            match target_ver {
+                3 => write_v3(&self, sink),
+                2 => write_v2(&self.to_version(), sink),
+                1 => write_v1(&self.to_version().to_version(), sink),
+                other => Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    format!("unknown target version {other}"),
+                )),
+            }
+        }
+    }
+
+    fn sample() -> FooV3 {
+        FooV3 {
+            val: 7,
+            extra: 9,
+            flag: 1,
+        }
+    }
+
+    #[test]
+    fn downgrade_to_latest_writes_every_field() {
+        let mut buf = Vec::new();
+        sample().downgrade_to(&mut buf, 3).unwrap();
+        assert_eq!(buf.len(), 4 + 2 + 1);
+    }
+
+    #[test]
+    fn downgrade_to_v2_walks_one_step_and_drops_flag() {
+        let mut buf = Vec::new();
+        sample().downgrade_to(&mut buf, 2).unwrap();
+        assert_eq!(buf.len(), 4 + 2);
+        assert_eq!(&buf[0..4], &7u32.to_le_bytes());
+        assert_eq!(&buf[4..6], &9u16.to_le_bytes());
+    }
+
+    #[test]
+    fn downgrade_to_v1_walks_the_whole_chain_and_drops_extra_and_flag() {
+        let mut buf = Vec::new();
+        sample().downgrade_to(&mut buf, 1).unwrap();
+        assert_eq!(buf, 7u32.to_le_bytes());
+    }
+
+    #[test]
+    fn unknown_target_version_errors() {
+        let mut buf = Vec::new();
+        let err = sample().downgrade_to(&mut buf, 0).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+    }
+}
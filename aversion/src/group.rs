@@ -0,0 +1,39 @@
+//! Deserializing a header-tagged message out of a group of message
+//! types.
+//!
+//! See the [crate documentation](crate) for the overall "message
+//! groups" pattern. [`GroupDeserialize`] is the trait a derive macro
+//! implements for an enum like `MyProtocol` in that example;
+//! [`read_message`] is the free function callers actually reach for.
+
+use std::io::Read;
+
+/// Deserialize whichever message a header identifies, upgraded to its
+/// family's latest version.
+///
+/// A derive macro generates this for an enum of message types: it reads
+/// the message header, matches the header's
+/// [`MessageId::MSG_ID`](crate::MessageId::MSG_ID) against each
+/// variant, and deserializes + upgrades that variant's payload via its
+/// [`UpgradeLatest`](crate::versioned::UpgradeLatest) implementation.
+pub trait GroupDeserialize: Sized {
+    /// The error type shared by every message variant's read path.
+    type Error;
+
+    /// Read one message from `reader` and return it as the
+    /// corresponding enum variant.
+    fn read_message<R: Read>(reader: R) -> Result<Self, Self::Error>;
+}
+
+/// Read one message of any type in `T`'s message group from `reader`.
+///
+/// This is just [`GroupDeserialize::read_message`] as a free function,
+/// so callers can write `let msg: MyProtocol = read_message(src)?;`
+/// without naming the trait.
+pub fn read_message<T, R>(reader: R) -> Result<T, T::Error>
+where
+    T: GroupDeserialize,
+    R: Read,
+{
+    T::read_message(reader)
+}
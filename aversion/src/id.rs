@@ -0,0 +1,13 @@
+//! The [`MessageId`] trait, which tags a message family for dispatch in
+//! a [`GroupDeserialize`](crate::group::GroupDeserialize) enum.
+
+/// A stable numeric identifier for a message family.
+///
+/// Implemented once per message family (not once per struct version), so
+/// `MSG_ID` identifies "this is a `Foo`", independent of which version
+/// of `Foo` is on the wire. See [`crate::assign_message_ids!`] for
+/// assigning several at once.
+pub trait MessageId {
+    /// This message family's identifier.
+    const MSG_ID: u16;
+}
@@ -103,10 +103,56 @@
 //! or `FooV2`) and `read_message` deserializes the correct version of the struct,
 //! upgrades it to the latest version, and returns it as a `MyProtocol`
 //! enum, for the caller to handle.
+//!
+//! ### Coordinated versions
+//!
+//! Each message family above versions independently: a `FooV2` can show up in
+//! the same stream as a `BarV1`, with no relationship between the two version
+//! numbers. For snapshot and rolling-upgrade use cases, it's sometimes useful
+//! to pin a coherent set of struct versions together under a single
+//! "application version" instead. See [`VersionMap`] for that.
+//!
+//! ### Downgrading
+//!
+//! Everything above is about reading old data with new code. The
+//! [`downgrade`] module covers the opposite direction: writing the
+//! latest in-memory struct back out in an older wire version, for
+//! peers that haven't upgraded yet.
+//!
+//! ### Nested fields
+//!
+//! A struct can also contain another versioned struct as a field. See
+//! [`nested`] for how to version those recursively, instead of writing
+//! the nested upgrade by hand.
+//!
+//! ### Single-source definitions
+//!
+//! Writing out `FooV1`, `FooV2`, ... by hand is still the rule today;
+//! [`schema`] sketches the `since`/`until` field-attribute scheme meant
+//! to generate them from one annotated struct instead.
+//!
+//! ### Non-contiguous versions
+//!
+//! The contiguous-versions rule above is the common case, but not a
+//! universal one: see [`versioned_type!`] for declaring gaps, when a
+//! version was retired or several message families share one
+//! version-numbering space.
+//!
+//! ### Revisions
+//!
+//! Not every change is a breaking one. [`revision`] covers additive,
+//! forward- and backward-compatible field changes that don't need a
+//! full version bump.
 
+pub mod downgrade;
 pub mod group;
 mod id;
+pub mod nested;
+pub mod revision;
+pub mod schema;
 mod versioned;
+pub mod version_map;
+pub mod versioned_type;
 
 #[doc(inline)]
 pub use crate::versioned::{FromVersion, IntoVersion, Versioned};
@@ -114,6 +160,24 @@ pub use crate::versioned::{FromVersion, IntoVersion, Versioned};
 #[doc(inline)]
 pub use crate::group::GroupDeserialize;
 
+#[doc(inline)]
+pub use crate::downgrade::{DowngradeLatest, GroupSerialize, ToVersion};
+
+#[doc(inline)]
+pub use crate::nested::NestedVersioned;
+
+#[doc(inline)]
+pub use crate::revision::Revisioned;
+
+#[doc(inline)]
+pub use crate::schema::FieldRange;
+
+#[doc(inline)]
+pub use crate::version_map::VersionMap;
+
+#[doc(inline)]
+pub use crate::versioned::UpgradeLatest;
+
 #[doc(inline)]
 pub use aversion_macros::{GroupDeserialize, UpgradeLatest, Versioned};
 
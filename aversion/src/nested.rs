@@ -0,0 +1,209 @@
+//! ## Versioning nested fields
+//!
+//! So far, every example has upgraded a struct as a single flat unit:
+//! `FromVersion<FooV1>` sees the whole of `FooV1` and produces the whole
+//! of `FooV2`. That works fine until one versioned struct contains
+//! another one as a field. If `BarV1` has a field of type `Foo`, then
+//! writing `impl FromVersion<BarV1> for BarV2` means hand-rolling the
+//! upgrade of that `Foo` field too, and the nested struct's own version
+//! is never recorded on the wire, so a reader has no way to know which
+//! version of `Foo` was embedded.
+//!
+//! A nested field can be versioned recursively instead: rather than
+//! being deserialized as a flat value, it's read by [`read_nested`]
+//! through its own [`UpgradeLatest`](crate::versioned::UpgradeLatest)
+//! implementation, with its version recorded alongside it on the wire.
+//! The derive macros don't recognise nested fields (there is no
+//! `#[aversion(nested)]` attribute), so a struct with a nested field has
+//! its `UpgradeLatest` implemented by hand, calling [`read_nested`] for
+//! that field:
+//! ```text
+//! struct BarV2 {
+//!     foo: Foo, // read with read_nested
+//!     name: String,
+//! }
+//! ```
+//! When [`GroupDeserialize::read_message`](crate::GroupDeserialize) (or
+//! a plain `Bar::upgrade_latest`) decodes a message containing this
+//! field, it descends into `foo`'s own per-field version, upgrades that
+//! field to its latest version on its own, and only then runs `Bar`'s
+//! `FromVersion` chain on the outer struct. This means a change to `Foo`
+//! doesn't force every struct that embeds it to bump its own version;
+//! the nested version travels with the field.
+//!
+//! A field read with [`read_nested`] must implement
+//! [`NestedVersioned`], which is automatically implemented for any type
+//! that already implements both
+//! [`Versioned`](crate::Versioned) and
+//! [`UpgradeLatest`](crate::versioned::UpgradeLatest).
+
+use std::io::Read;
+
+use crate::versioned::UpgradeLatest;
+use crate::Versioned;
+
+/// Marker trait for types that can be used as a `#[aversion(nested)]`
+/// field.
+///
+/// A nested field is deserialized through its own version header rather
+/// than as a flat value, so it must be both [`Versioned`] (so it has a
+/// per-field version to record) and [`UpgradeLatest`] (so it can be
+/// upgraded to its latest version on its own, before the outer struct's
+/// `FromVersion` chain runs). There's nothing to implement here directly
+/// -- it's blanket-implemented for anything that already satisfies those
+/// bounds.
+pub trait NestedVersioned: Versioned + UpgradeLatest {}
+
+impl<T: Versioned + UpgradeLatest> NestedVersioned for T {}
+
+/// Read a `#[aversion(nested)]` field: its own wire version, followed by
+/// its payload, upgraded to `T`'s latest version through `T`'s own
+/// [`UpgradeLatest`] implementation.
+///
+/// A hand-written `UpgradeLatest` impl calls this for every nested
+/// field, in place of reading the field as a flat value. The field's
+/// wire version travels with it on the wire and is read here, completely
+/// independent of whatever wire version the outer struct itself is
+/// being read as.
+pub fn read_nested<T, R>(mut reader: R) -> Result<T, T::Error>
+where
+    T: NestedVersioned,
+    R: Read,
+    T::Error: From<std::io::Error>,
+{
+    let mut ver_buf = [0u8; 2];
+    reader.read_exact(&mut ver_buf)?;
+    let wire_version = u16::from_le_bytes(ver_buf);
+    T::upgrade_latest(reader, wire_version)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::FromVersion;
+    use std::io::{self, Cursor};
+
+    // The nested field's own family: versioned and upgraded completely
+    // independently of whatever outer struct embeds it.
+    struct FooV1 {
+        val: u32,
+    }
+    #[derive(Debug, PartialEq)]
+    struct FooV2 {
+        val: u64,
+    }
+
+    impl Versioned for FooV1 {
+        const VER: u16 = 1;
+    }
+    impl Versioned for FooV2 {
+        const VER: u16 = 2;
+    }
+    impl FromVersion<FooV1> for FooV2 {
+        fn from_version(v: FooV1) -> Self {
+            FooV2 { val: v.val.into() }
+        }
+    }
+    impl UpgradeLatest for FooV2 {
+        type Error = io::Error;
+
+        fn upgrade_latest<R: Read>(mut reader: R, wire_version: u16) -> io::Result<Self> {
+            use crate::IntoVersion;
+
+            match wire_version {
+                1 => {
+                    let mut buf = [0u8; 4];
+                    reader.read_exact(&mut buf)?;
+                    Ok(FooV1 {
+                        val: u32::from_le_bytes(buf),
+                    }
+                    .into_version())
+                }
+                2 => {
+                    let mut buf = [0u8; 8];
+                    reader.read_exact(&mut buf)?;
+                    Ok(FooV2 {
+                        val: u64::from_le_bytes(buf),
+                    })
+                }
+                other => Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("unknown wire version {other}"),
+                )),
+            }
+        }
+    }
+
+    // The outer struct, with `foo` as a nested field read via `read_nested`.
+    #[derive(Debug, PartialEq)]
+    struct BarV2 {
+        foo: FooV2,
+        name: String,
+    }
+
+    impl Versioned for BarV2 {
+        const VER: u16 = 2;
+    }
+    impl UpgradeLatest for BarV2 {
+        type Error = io::Error;
+
+        fn upgrade_latest<R: Read>(mut reader: R, wire_version: u16) -> io::Result<Self> {
+            match wire_version {
+                2 => {
+                    let foo: FooV2 = read_nested(&mut reader)?;
+
+                    let mut len_buf = [0u8; 4];
+                    reader.read_exact(&mut len_buf)?;
+                    let len = u32::from_le_bytes(len_buf) as usize;
+                    let mut name_buf = vec![0u8; len];
+                    reader.read_exact(&mut name_buf)?;
+                    let name = String::from_utf8(name_buf)
+                        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+
+                    Ok(BarV2 { foo, name })
+                }
+                other => Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("unknown wire version {other}"),
+                )),
+            }
+        }
+    }
+
+    #[test]
+    fn nested_field_upgrades_through_its_own_version_chain() {
+        let mut bytes = Vec::new();
+        // foo's own wire version (1), independent of Bar's wire version (2).
+        bytes.extend_from_slice(&1u16.to_le_bytes());
+        bytes.extend_from_slice(&7u32.to_le_bytes());
+        // Bar's remaining fields.
+        let name = "hi";
+        bytes.extend_from_slice(&(name.len() as u32).to_le_bytes());
+        bytes.extend_from_slice(name.as_bytes());
+
+        let bar = BarV2::upgrade_latest(Cursor::new(bytes), 2).unwrap();
+        assert_eq!(bar.foo, FooV2 { val: 7 });
+        assert_eq!(bar.name, "hi");
+    }
+
+    #[test]
+    fn nested_field_already_at_latest_version_skips_upgrade() {
+        let mut bytes = Vec::new();
+        bytes.extend_from_slice(&2u16.to_le_bytes());
+        bytes.extend_from_slice(&7u64.to_le_bytes());
+        let name = "hi";
+        bytes.extend_from_slice(&(name.len() as u32).to_le_bytes());
+        bytes.extend_from_slice(name.as_bytes());
+
+        let bar = BarV2::upgrade_latest(Cursor::new(bytes), 2).unwrap();
+        assert_eq!(bar.foo, FooV2 { val: 7 });
+    }
+
+    #[test]
+    fn unknown_nested_version_errors() {
+        let mut bytes = Vec::new();
+        bytes.extend_from_slice(&9u16.to_le_bytes());
+        let err = BarV2::upgrade_latest(Cursor::new(bytes), 2).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    }
+}
@@ -0,0 +1,254 @@
+//! ## Revisions: additive, forward- and backward-compatible changes
+//!
+//! Every example so far treats a version bump as a breaking change: a
+//! `FooV1` and a `FooV2` are different types, and moving between them
+//! always goes through [`FromVersion`](crate::FromVersion). That's the
+//! right tool when a field's meaning or type actually changes, but it's
+//! overkill for the common case of just appending a new field with a
+//! sensible default. Following the distinction used by Pravega's
+//! serializer, this module separates that case out as a **revision**:
+//! an additive change within a single struct *version*, rather than a
+//! new version of its own.
+//!
+//! [`Revisioned::REVISION`] records how many fields a struct version has
+//! grown since it was first introduced:
+//! ```
+//! use aversion::revision::Revisioned;
+//! # use aversion::Versioned;
+//! # struct FooV2 { a: u32, b: u32, c: u32 }
+//! # impl Versioned for FooV2 { const VER: u16 = 2; }
+//! impl Revisioned for FooV2 {
+//!     // `c` was added after `a` and `b` shipped, without bumping VER.
+//!     const REVISION: u16 = 1;
+//! }
+//! ```
+//! A reader doesn't need `REVISION` read off the wire to know whether it's
+//! looking at an older or newer revision than its own -- the message's
+//! framed byte length (see [`write_framed`]/[`read_framed`]) already says
+//! that, relative to how many bytes the reader's own fields occupy. On
+//! read, via [`revision_cursor`]:
+//! - if the frame is *longer* than the fields the reader knows about
+//!   (a newer revision was written), the surplus trailing bytes are
+//!   silently discarded rather than read as a field;
+//! - if the frame is *shorter* (an older revision was written), the
+//!   missing trailing bytes read back as zero, which is `Default` for
+//!   every primitive numeric field a revision bump can append.
+//!
+//! `REVISION` itself is still worth recording on the type, even though
+//! no function here takes it as a parameter: it's what tells a developer
+//! adding a field whether the change is allowed to be additive-only (in
+//! which case `REVISION` goes up) or needs a real `FromVersion` bump
+//! (in which case `VER` does).
+
+use std::io::{self, Cursor, Read, Write};
+
+/// The revision of a struct version: how many additive, trailing-field
+/// changes it has accumulated since the version was introduced.
+///
+/// Unlike [`Versioned::VER`](crate::Versioned::VER), bumping a type's
+/// `REVISION` is not a breaking change -- it only ever appends
+/// `Default`-able fields, so both older and newer readers can make sense
+/// of both older and newer payloads.
+pub trait Revisioned: crate::Versioned {
+    /// How many revisions this struct version has accumulated.
+    ///
+    /// Starts at `0` for the revision a struct version was introduced
+    /// with.
+    const REVISION: u16;
+}
+
+/// Write `payload` (the already-serialized bytes of a message) prefixed
+/// with its length, so a reader can always tell exactly where the
+/// message ends.
+pub fn write_framed<W: Write>(writer: &mut W, payload: &[u8]) -> io::Result<()> {
+    let len: u32 = payload
+        .len()
+        .try_into()
+        .map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, "payload too large to frame"))?;
+    writer.write_all(&len.to_le_bytes())?;
+    writer.write_all(payload)
+}
+
+/// Read back a length-prefixed frame written by [`write_framed`],
+/// returning the raw message bytes.
+///
+/// `max_len` bounds the frame length this call is willing to allocate
+/// for; a corrupt or malicious length prefix up to `u32::MAX` would
+/// otherwise trigger an allocation of that size before a single payload
+/// byte has even been validated. Pass the largest frame the caller
+/// legitimately expects to see (or the reader's known remaining length,
+/// if that's tighter). A length prefix over `max_len` is reported as
+/// [`io::ErrorKind::InvalidData`], not allocated.
+///
+/// The caller is responsible for deserializing the returned bytes into
+/// the expected struct, tolerating a frame that's shorter or longer than
+/// the fields it knows about, per the [module documentation](self).
+pub fn read_framed<R: Read>(reader: &mut R, max_len: u32) -> io::Result<Vec<u8>> {
+    let mut len_buf = [0u8; 4];
+    reader.read_exact(&mut len_buf)?;
+    let len = u32::from_le_bytes(len_buf);
+    if len > max_len {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("frame length {len} exceeds max_len {max_len}"),
+        ));
+    }
+    let mut payload = vec![0u8; len as usize];
+    reader.read_exact(&mut payload)?;
+    Ok(payload)
+}
+
+/// Turn a frame's raw bytes into a reader over exactly `known_len` bytes,
+/// tolerating a revision mismatch between writer and reader.
+///
+/// `known_len` is the byte length of the fields the reader's own struct
+/// revision actually decodes. If `payload` is longer (a newer revision
+/// was written), the surplus trailing bytes -- fields this reader
+/// doesn't know about -- are dropped. If `payload` is shorter (an older
+/// revision was written), it's padded with zero bytes, so any field the
+/// reader expects but the writer didn't send decodes as `0`
+/// (`Default` for every primitive numeric type a revision bump can add).
+pub fn revision_cursor(mut payload: Vec<u8>, known_len: usize) -> Cursor<Vec<u8>> {
+    payload.resize(known_len, 0);
+    Cursor::new(payload)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MAX_LEN: u32 = 1024;
+
+    #[test]
+    fn round_trips_payload() {
+        let mut buf = Vec::new();
+        write_framed(&mut buf, b"hello").unwrap();
+
+        let mut cursor = io::Cursor::new(buf);
+        let payload = read_framed(&mut cursor, MAX_LEN).unwrap();
+        assert_eq!(payload, b"hello");
+    }
+
+    #[test]
+    fn distinguishes_surplus_from_truncated() {
+        // A "newer" writer appends extra trailing bytes the reader
+        // doesn't know about; the frame still reports the full length.
+        let mut newer = Vec::new();
+        write_framed(&mut newer, b"ab-extra").unwrap();
+        let mut cursor = io::Cursor::new(newer);
+        assert_eq!(read_framed(&mut cursor, MAX_LEN).unwrap(), b"ab-extra");
+
+        // An "older" writer wrote fewer bytes; the frame reports that
+        // shorter length rather than the reader's expected size.
+        let mut older = Vec::new();
+        write_framed(&mut older, b"a").unwrap();
+        let mut cursor = io::Cursor::new(older);
+        assert_eq!(read_framed(&mut cursor, MAX_LEN).unwrap(), b"a");
+    }
+
+    #[test]
+    fn errors_on_truncated_frame() {
+        let mut buf = Vec::new();
+        write_framed(&mut buf, b"hello").unwrap();
+        buf.truncate(buf.len() - 2); // drop the last 2 payload bytes
+        let mut cursor = io::Cursor::new(buf);
+        assert!(read_framed(&mut cursor, MAX_LEN).is_err());
+    }
+
+    #[test]
+    fn rejects_frame_length_over_max_without_allocating() {
+        // A corrupt or hostile length prefix claiming close to u32::MAX
+        // must be rejected before any allocation is attempted.
+        let mut buf = Vec::new();
+        buf.extend_from_slice(&u32::MAX.to_le_bytes());
+        let mut cursor = io::Cursor::new(buf);
+        let err = read_framed(&mut cursor, MAX_LEN).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    }
+
+    // FooV2 revision 1 appended `c` after `a` and `b` shipped at
+    // revision 0, without bumping VER -- the example from the module
+    // doc, read for real via `revision_cursor`.
+    struct FooV2 {
+        a: u32,
+        b: u32,
+        c: u32,
+    }
+
+    impl crate::Versioned for FooV2 {
+        const VER: u16 = 2;
+    }
+    impl Revisioned for FooV2 {
+        const REVISION: u16 = 1;
+    }
+
+    const FOO_V2_REVISION_0_LEN: usize = 8; // a, b
+    const FOO_V2_REVISION_1_LEN: usize = 12; // a, b, c
+
+    // `known_revision` stands in for the revision the *reader's own
+    // code* was compiled against: it only ever reads the fields that
+    // revision knows about, regardless of what the writer actually
+    // sent. `revision_cursor` is what makes that safe either way.
+    fn read_foo_v2(mut reader: impl Read, known_revision: u16) -> io::Result<FooV2> {
+        let payload = read_framed(&mut reader, 1024)?;
+        let known_len = if known_revision >= 1 {
+            FOO_V2_REVISION_1_LEN
+        } else {
+            FOO_V2_REVISION_0_LEN
+        };
+        let mut cursor = revision_cursor(payload, known_len);
+        let mut buf = [0u8; 4];
+        cursor.read_exact(&mut buf)?;
+        let a = u32::from_le_bytes(buf);
+        cursor.read_exact(&mut buf)?;
+        let b = u32::from_le_bytes(buf);
+        let c = if known_revision >= 1 {
+            cursor.read_exact(&mut buf)?;
+            u32::from_le_bytes(buf)
+        } else {
+            0
+        };
+        Ok(FooV2 { a, b, c })
+    }
+
+    #[test]
+    fn reads_an_older_revision_with_missing_field_defaulted() {
+        // A revision-0 writer only ever wrote `a` and `b`.
+        let mut wire = Vec::new();
+        let mut payload = Vec::new();
+        payload.extend_from_slice(&1u32.to_le_bytes());
+        payload.extend_from_slice(&2u32.to_le_bytes());
+        write_framed(&mut wire, &payload).unwrap();
+
+        let foo = read_foo_v2(io::Cursor::new(wire), FooV2::REVISION).unwrap();
+        assert_eq!((foo.a, foo.b, foo.c), (1, 2, 0));
+    }
+
+    #[test]
+    fn reads_a_newer_revision_with_surplus_field_discarded() {
+        // A revision-1 writer also wrote `c`, but this reader only
+        // knows about revision 0's two fields.
+        let mut wire = Vec::new();
+        let mut payload = Vec::new();
+        payload.extend_from_slice(&1u32.to_le_bytes());
+        payload.extend_from_slice(&2u32.to_le_bytes());
+        payload.extend_from_slice(&3u32.to_le_bytes());
+        write_framed(&mut wire, &payload).unwrap();
+
+        let foo = read_foo_v2(io::Cursor::new(wire), 0).unwrap();
+        assert_eq!((foo.a, foo.b, foo.c), (1, 2, 0));
+    }
+
+    #[test]
+    fn reads_matching_revision_unchanged() {
+        let mut wire = Vec::new();
+        let mut payload = Vec::new();
+        payload.extend_from_slice(&1u32.to_le_bytes());
+        payload.extend_from_slice(&2u32.to_le_bytes());
+        payload.extend_from_slice(&3u32.to_le_bytes());
+        write_framed(&mut wire, &payload).unwrap();
+
+        let foo = read_foo_v2(io::Cursor::new(wire), FooV2::REVISION).unwrap();
+        assert_eq!((foo.a, foo.b, foo.c), (1, 2, 3));
+    }
+}
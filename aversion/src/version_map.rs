@@ -0,0 +1,323 @@
+//! ## Coordinated schema versions
+//!
+//! [`Versioned`] lets each message family track its own struct version
+//! independently, and that's normally all you need: a reader just asks
+//! for "the latest" and upgrades on the fly. But for snapshot and
+//! rolling-upgrade scenarios, independent versioning isn't quite enough:
+//! we'd like to pin a whole *set* of struct versions together, so that a
+//! reader can say "interpret this stream as application version 7" and
+//! know exactly which struct version to expect for every message type,
+//! even ones that haven't changed since version 3.
+//!
+//! [`VersionMap`] is a table, indexed by application version, where each
+//! row records the struct version that every registered [`MessageId`]
+//! type should use at that application version. Building the table only
+//! requires listing the versions where a type actually changed; any
+//! version with no explicit entry for a type simply inherits the value
+//! from the nearest lower row. This mirrors the approach used by
+//! Firecracker's snapshot format, where a single version number pins a
+//! coherent set of struct versions across the whole snapshot.
+//!
+//! ```
+//! use aversion::VersionMap;
+//!
+//! // Application version 1 shipped FooV1 and BarV1.
+//! // Application version 2 only changed Foo (to FooV2); Bar stayed at BarV1.
+//! // Application version 3 changed both.
+//! let map = VersionMap::builder()
+//!     .set(1, 100 /* Foo's MSG_ID */, 1)
+//!     .set(1, 101 /* Bar's MSG_ID */, 1)
+//!     .set(2, 100, 2)
+//!     .set(3, 100, 3)
+//!     .set(3, 101, 2)
+//!     .build();
+//!
+//! // At app version 2, Bar is still expected to be BarV1.
+//! assert_eq!(map.struct_version(2, 101), Some(1));
+//! // At app version 3, Foo is expected to be FooV3.
+//! assert_eq!(map.struct_version(3, 100), Some(3));
+//! ```
+//!
+//! A [`VersionMap`] doesn't replace the per-message `msg_ver` carried in
+//! the wire header; it's an additional constraint a reader can apply
+//! instead of trusting only what the header claims. [`read_at_version`]
+//! threads a `VersionMap` through
+//! [`UpgradeLatest::upgrade_latest`](crate::versioned::UpgradeLatest):
+//! rather than reading `wire_version` off a header, it looks `msg_id` up
+//! in the map at a chosen application version and upgrades from there.
+//! ```
+//! use aversion::{FromVersion, IntoVersion, UpgradeLatest, Versioned, VersionMap};
+//! use aversion::version_map::read_at_version;
+//! use std::io::Read;
+//!
+//! struct FooV1 { val: u32 }
+//! struct FooV2 { val: u32 }
+//! impl Versioned for FooV1 { const VER: u16 = 1; }
+//! impl Versioned for FooV2 { const VER: u16 = 2; }
+//! impl FromVersion<FooV1> for FooV2 {
+//!     fn from_version(v: FooV1) -> Self { FooV2 { val: v.val } }
+//! }
+//! impl UpgradeLatest for FooV2 {
+//!     type Error = std::io::Error;
+//!     fn upgrade_latest<R: Read>(mut reader: R, wire_version: u16) -> std::io::Result<Self> {
+//!         let mut buf = [0u8; 4];
+//!         reader.read_exact(&mut buf)?;
+//!         let val = u32::from_le_bytes(buf);
+//!         match wire_version {
+//!             1 => Ok(FooV1 { val }.into_version()),
+//!             2 => Ok(FooV2 { val }),
+//!             other => Err(std::io::Error::new(
+//!                 std::io::ErrorKind::InvalidData,
+//!                 format!("unknown wire version {other}"),
+//!             )),
+//!         }
+//!     }
+//! }
+//!
+//! let map = VersionMap::builder().set(1, 100, 1).set(2, 100, 2).build();
+//! let wire_bytes = 42u32.to_le_bytes();
+//!
+//! // At application version 1, Foo's header-free payload is read as FooV1
+//! // and upgraded, even though no wire header ever said "version 1" here.
+//! let msg: FooV2 = read_at_version(std::io::Cursor::new(wire_bytes), &map, 1, 100).unwrap();
+//! assert_eq!(msg.val, 42);
+//! ```
+//! Writers use the same table in reverse, picking the struct version for
+//! each message type at a chosen application version before serializing
+//! (see the downgrade path in [`crate::downgrade`]).
+
+use std::collections::BTreeMap;
+use std::io::Read;
+
+use crate::versioned::UpgradeLatest;
+
+/// A coordinated table of application version -> struct version, per
+/// registered [`MessageId`](crate::MessageId).
+///
+/// See the [module documentation](self) for the motivation and an
+/// example. Construct one with [`VersionMap::builder`].
+#[derive(Debug, Clone, Default)]
+pub struct VersionMap {
+    /// `rows[msg_id]` is a sorted map of `app_version -> struct_version`,
+    /// recording only the application versions where `msg_id`'s struct
+    /// version actually changed.
+    rows: BTreeMap<u16, BTreeMap<u32, u16>>,
+}
+
+impl VersionMap {
+    /// Start building a new `VersionMap`.
+    pub fn builder() -> VersionMapBuilder {
+        VersionMapBuilder::default()
+    }
+
+    /// Look up the struct version that `msg_id` should use at the given
+    /// application version.
+    ///
+    /// Returns the value set at `app_version`, or if none was set
+    /// exactly there, the value from the nearest lower application
+    /// version. Returns `None` if `msg_id` has no entry at or before
+    /// `app_version`.
+    pub fn struct_version(&self, app_version: u32, msg_id: u16) -> Option<u16> {
+        self.rows
+            .get(&msg_id)?
+            .range(..=app_version)
+            .next_back()
+            .map(|(_, struct_ver)| *struct_ver)
+    }
+
+    /// The highest application version with any entries in this map.
+    pub fn max_app_version(&self) -> Option<u32> {
+        self.rows
+            .values()
+            .filter_map(|versions| versions.keys().next_back().copied())
+            .max()
+    }
+}
+
+/// Builder for [`VersionMap`].
+///
+/// Entries may be added in any order; only the application versions
+/// where a type's struct version actually changes need to be recorded.
+#[derive(Debug, Clone, Default)]
+pub struct VersionMapBuilder {
+    rows: BTreeMap<u16, BTreeMap<u32, u16>>,
+}
+
+impl VersionMapBuilder {
+    /// Record that, starting at `app_version`, `msg_id` should be read
+    /// or written as `struct_version`.
+    pub fn set(mut self, app_version: u32, msg_id: u16, struct_version: u16) -> Self {
+        self.rows
+            .entry(msg_id)
+            .or_default()
+            .insert(app_version, struct_version);
+        self
+    }
+
+    /// Finish building the `VersionMap`.
+    pub fn build(self) -> VersionMap {
+        VersionMap { rows: self.rows }
+    }
+}
+
+/// The error returned by [`read_at_version`]: either `map` has no entry
+/// for `msg_id` at or before `app_version`, or the matching
+/// [`UpgradeLatest`] implementation itself failed.
+#[derive(Debug)]
+pub enum ReadAtVersionError<E> {
+    /// `map` has no struct version recorded for `msg_id` at or before
+    /// `app_version`.
+    NoVersionForAppVersion {
+        /// The application version that was looked up.
+        app_version: u32,
+        /// The message family that had no entry.
+        msg_id: u16,
+    },
+    /// The underlying [`UpgradeLatest::upgrade_latest`] call failed.
+    Upgrade(E),
+}
+
+impl<E: std::fmt::Display> std::fmt::Display for ReadAtVersionError<E> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ReadAtVersionError::NoVersionForAppVersion { app_version, msg_id } => write!(
+                f,
+                "no struct version recorded for msg_id {msg_id} at or before app version {app_version}"
+            ),
+            ReadAtVersionError::Upgrade(e) => e.fmt(f),
+        }
+    }
+}
+
+impl<E: std::fmt::Debug + std::fmt::Display> std::error::Error for ReadAtVersionError<E> {}
+
+/// Read a `T` from `reader`, using `map` to decide which wire version to
+/// expect for `msg_id` at `app_version`, instead of trusting a version
+/// recorded in a message header.
+///
+/// See the [module documentation](self) for a full example.
+pub fn read_at_version<T, R>(
+    reader: R,
+    map: &VersionMap,
+    app_version: u32,
+    msg_id: u16,
+) -> Result<T, ReadAtVersionError<T::Error>>
+where
+    T: UpgradeLatest,
+    R: Read,
+{
+    let wire_version = map
+        .struct_version(app_version, msg_id)
+        .ok_or(ReadAtVersionError::NoVersionForAppVersion { app_version, msg_id })?;
+    T::upgrade_latest(reader, wire_version).map_err(ReadAtVersionError::Upgrade)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn inherits_from_lower_row() {
+        let map = VersionMap::builder()
+            .set(1, 100, 1)
+            .set(1, 101, 1)
+            .set(2, 100, 2)
+            .build();
+
+        assert_eq!(map.struct_version(1, 100), Some(1));
+        assert_eq!(map.struct_version(2, 100), Some(2));
+        // Bar never changed, so it inherits the version 1 entry.
+        assert_eq!(map.struct_version(2, 101), Some(1));
+        assert_eq!(map.struct_version(5, 101), Some(1));
+    }
+
+    #[test]
+    fn unknown_before_first_entry() {
+        let map = VersionMap::builder().set(3, 100, 1).build();
+        assert_eq!(map.struct_version(1, 100), None);
+        assert_eq!(map.struct_version(3, 100), Some(1));
+    }
+
+    #[test]
+    fn unknown_message_id() {
+        let map = VersionMap::builder().set(1, 100, 1).build();
+        assert_eq!(map.struct_version(1, 999), None);
+    }
+
+    #[test]
+    fn max_app_version() {
+        let map = VersionMap::builder()
+            .set(1, 100, 1)
+            .set(3, 101, 2)
+            .build();
+        assert_eq!(map.max_app_version(), Some(3));
+    }
+
+    struct FooV1 {
+        val: u32,
+    }
+    #[derive(Debug)]
+    struct FooV2 {
+        val: u32,
+    }
+
+    impl crate::Versioned for FooV1 {
+        const VER: u16 = 1;
+    }
+    impl crate::Versioned for FooV2 {
+        const VER: u16 = 2;
+    }
+    impl crate::FromVersion<FooV1> for FooV2 {
+        fn from_version(v: FooV1) -> Self {
+            FooV2 { val: v.val }
+        }
+    }
+    impl UpgradeLatest for FooV2 {
+        type Error = std::io::Error;
+
+        fn upgrade_latest<R: Read>(mut reader: R, wire_version: u16) -> std::io::Result<Self> {
+            use crate::IntoVersion;
+
+            let mut buf = [0u8; 4];
+            reader.read_exact(&mut buf)?;
+            let val = u32::from_le_bytes(buf);
+            match wire_version {
+                1 => Ok(FooV1 { val }.into_version()),
+                2 => Ok(FooV2 { val }),
+                other => Err(std::io::Error::new(
+                    std::io::ErrorKind::InvalidData,
+                    format!("unknown wire version {other}"),
+                )),
+            }
+        }
+    }
+
+    #[test]
+    fn pins_wire_version_from_app_version_not_a_header() {
+        let map = VersionMap::builder().set(1, 100, 1).set(2, 100, 2).build();
+        let bytes = 42u32.to_le_bytes();
+
+        // At app version 1, msg_id 100 maps to struct version 1, so this
+        // reads a FooV1 off the wire and upgrades it via FromVersion,
+        // even though nothing in `bytes` says "version 1".
+        let msg: FooV2 = read_at_version(std::io::Cursor::new(bytes), &map, 1, 100).unwrap();
+        assert_eq!(msg.val, 42);
+
+        // At app version 2, the same msg_id now maps to struct version 2.
+        let msg: FooV2 = read_at_version(std::io::Cursor::new(bytes), &map, 2, 100).unwrap();
+        assert_eq!(msg.val, 42);
+    }
+
+    #[test]
+    fn unknown_app_version_does_not_call_upgrade_latest() {
+        let map = VersionMap::builder().set(1, 100, 1).build();
+        let err = read_at_version::<FooV2, _>(std::io::Cursor::new([]), &map, 0, 100).unwrap_err();
+        assert!(matches!(
+            err,
+            ReadAtVersionError::NoVersionForAppVersion {
+                app_version: 0,
+                msg_id: 100
+            }
+        ));
+    }
+}
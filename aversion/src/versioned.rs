@@ -0,0 +1,72 @@
+//! Core traits for tracking a struct's version and upgrading it.
+//!
+//! See the [crate documentation](crate) for the overall pattern; this
+//! module just holds the traits themselves.
+
+use std::io::Read;
+
+/// A struct that has a wire version.
+///
+/// Implemented by `#[derive(Versioned)]` on each concrete version of a
+/// struct, e.g. `FooV1`, `FooV2`.
+pub trait Versioned {
+    /// This struct's version number.
+    const VER: u16;
+}
+
+/// Upgrade an older version of a struct into a newer one.
+///
+/// `impl FromVersion<FooV1> for FooV2` means "a `FooV2` can be produced
+/// from a `FooV1`". Implemented by hand for each pair of consecutive
+/// versions; see the [crate documentation](crate) for an example.
+pub trait FromVersion<T> {
+    /// Upgrade `v` into `Self`.
+    fn from_version(v: T) -> Self;
+}
+
+/// Every type trivially upgrades from itself. This is what lets
+/// `upgrade_latest` treat "the wire version is already the latest
+/// version" the same as any other step of the upgrade chain, with no
+/// special case.
+impl<T> FromVersion<T> for T {
+    fn from_version(v: T) -> T {
+        v
+    }
+}
+
+/// The opposite direction of [`FromVersion`]: convert `self` into `T`.
+///
+/// This is blanket-implemented for any `T: FromVersion<Self>`, so users
+/// only ever need to implement `FromVersion`, and can call either
+/// `FooV2::from_version(v1)` or `v1.into_version()`, whichever reads
+/// better at the call site.
+pub trait IntoVersion<T> {
+    /// Upgrade `self` into `T`.
+    fn into_version(self) -> T;
+}
+
+impl<T, U> IntoVersion<U> for T
+where
+    U: FromVersion<T>,
+{
+    fn into_version(self) -> U {
+        U::from_version(self)
+    }
+}
+
+/// Deserialize any wire version of a struct, and upgrade it to the
+/// latest version.
+///
+/// A derive macro generates this for the latest version of a struct
+/// family: given the wire version recorded in a message header, it
+/// deserializes the matching concrete struct (`FooV1`, `FooV2`, ...) and
+/// walks the [`FromVersion`] chain up to `Self`.
+pub trait UpgradeLatest: Sized {
+    /// The error type returned when `wire_version` is unrecognized, or
+    /// the underlying reader fails.
+    type Error;
+
+    /// Read a message of any known wire version from `reader`, and
+    /// upgrade it to the latest version.
+    fn upgrade_latest<R: Read>(reader: R, wire_version: u16) -> Result<Self, Self::Error>;
+}
@@ -0,0 +1,233 @@
+//! ## Non-contiguous version ranges
+//!
+//! [`Versioned`](crate::Versioned) mandates that struct versions start
+//! at 1 and be contiguous: `FooV1`, `FooV2`, `FooV3`, with no gaps. That
+//! breaks down in a couple of real situations: a version can be retired
+//! (its struct deleted, but old data written in that shape may still
+//! need to be read), or several message families can share one
+//! version-numbering space on disk, where not every family changes at
+//! every number.
+//!
+//! [`versioned_type!`] (in the style of Fuchsia's on-disk
+//! `versioned_type!` macro) declares the mapping from a range of wire
+//! versions directly to the struct that should be used to read them,
+//! without requiring one struct per version number:
+//! ```
+//! use aversion::versioned_type;
+//! # use aversion::{FromVersion, Versioned};
+//! # #[derive(Versioned)] struct FooV1 { val: u32 }
+//! # #[derive(Versioned)] struct FooV3 { val: u32, name: String }
+//! # #[derive(Versioned)] struct FooV5 { val: u64, name: String }
+//! # impl FromVersion<FooV1> for FooV3 {
+//! #     fn from_version(v: FooV1) -> Self { FooV3 { val: v.val, name: String::new() } }
+//! # }
+//! # impl FromVersion<FooV3> for FooV5 {
+//! #     fn from_version(v: FooV3) -> Self { FooV5 { val: v.val.into(), name: v.name } }
+//! # }
+//! # impl FromVersion<FooV1> for FooV5 {
+//! #     fn from_version(v: FooV1) -> Self { FooV5::from_version(FooV3::from_version(v)) }
+//! # }
+//! # fn read_v5<R: std::io::Read>(_r: &mut R) -> std::io::Result<FooV5> { unimplemented!() }
+//! # fn read_v3<R: std::io::Read>(_r: &mut R) -> std::io::Result<FooV3> { unimplemented!() }
+//! # fn read_v1<R: std::io::Read>(_r: &mut R) -> std::io::Result<FooV1> { unimplemented!() }
+//! versioned_type! {
+//!     latest = FooV5,
+//!     error = std::io::Error,
+//!     fn_name = upgrade_foo,
+//!     5.. => FooV5, using read_v5,
+//!     3.. => FooV3, using read_v3,
+//!     1.. => FooV1, using read_v1,
+//! }
+//! ```
+//! Each arm gives the lowest wire version that should be read using the
+//! struct on the right; there is deliberately no `FooV2` or `FooV4`
+//! here, because those version numbers were simply never used. The
+//! macro expands to a function (named `fn_name`) that, given a wire
+//! version, picks the arm with the *highest* lower bound that is still
+//! `<=` that version, deserializes with the `using` function declared
+//! for that arm, and converts the result to `latest` with a single
+//! [`FromVersion`](crate::FromVersion) step. Intermediate versions are
+//! not walked: `latest` must implement `FromVersion` for every arm's
+//! struct directly (above, `FooV5: FromVersion<FooV1>` as well as
+//! `FooV5: FromVersion<FooV3>`). Arms may be listed
+//! in any order -- the generated function compares every arm's lower
+//! bound before picking one, rather than simply taking the first match
+//! in source order, so writing them descending (as above) or ascending
+//! makes no difference to which struct is selected.
+//!
+//! Every arm's `using` function must return the same `error` type,
+//! named once up front rather than inferred: nothing in the generated
+//! function ties a per-arm error type to anything else, so leaving it
+//! generic would make `VersionedTypeError<E>` unconstrained and the
+//! expansion wouldn't type-check. If two arms genuinely need different
+//! error types, have their `using` functions map to a common error
+//! type (e.g. `Box<dyn std::error::Error>`) before returning.
+//!
+//! Requiring an explicit `using` function on every arm, rather than
+//! assuming one fixed serialization format, is what lets a particular
+//! range use a one-off decoding routine for formats whose on-disk layout
+//! changed even though the logical struct didn't -- there's no special
+//! case needed, every arm already looks the same.
+
+/// The error returned by a function generated by [`versioned_type!`]
+/// when a wire version doesn't fall into any declared range.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct UnknownWireVersion {
+    /// The wire version that had no matching range.
+    pub wire_version: u16,
+}
+
+impl std::fmt::Display for UnknownWireVersion {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "wire version {} does not fall within any declared versioned_type! range",
+            self.wire_version
+        )
+    }
+}
+
+impl std::error::Error for UnknownWireVersion {}
+
+/// Declare a non-contiguous mapping from wire version ranges to structs,
+/// and generate a function that reads, and upgrades, whichever one
+/// matches.
+///
+/// See the [module documentation](self) for the motivation and syntax.
+#[macro_export]
+macro_rules! versioned_type {
+    (
+        latest = $latest:ty,
+        error = $err:ty,
+        fn_name = $fn_name:ident,
+        $( $min:literal .. => $ty:ty, using $read:path ),+ $(,)?
+    ) => {
+        fn $fn_name<R>(
+            mut reader: R,
+            wire_version: u16,
+        ) -> ::std::result::Result<$latest, $crate::versioned_type::VersionedTypeError<$err>>
+        where
+            R: ::std::io::Read,
+        {
+            // Find the arm with the highest lower bound that is still
+            // `<= wire_version`, regardless of the order the arms were
+            // declared in.
+            let mut selected: ::std::option::Option<u16> = ::std::option::Option::None;
+            $(
+                if wire_version >= $min && selected.map_or(true, |s| $min > s) {
+                    selected = ::std::option::Option::Some($min);
+                }
+            )+
+            match selected {
+                $(
+                    ::std::option::Option::Some($min) => {
+                        let value: $ty = $read(&mut reader)
+                            .map_err($crate::versioned_type::VersionedTypeError::Read)?;
+                        return ::std::result::Result::Ok($crate::IntoVersion::into_version(value));
+                    }
+                )+
+                _ => {}
+            }
+            ::std::result::Result::Err(
+                $crate::versioned_type::VersionedTypeError::UnknownWireVersion(
+                    $crate::versioned_type::UnknownWireVersion { wire_version },
+                ),
+            )
+        }
+    };
+}
+
+/// The error type returned by a [`versioned_type!`]-generated function:
+/// either no arm matched the wire version, or the matching arm's
+/// `using` function failed.
+#[derive(Debug)]
+pub enum VersionedTypeError<E> {
+    /// No declared range covered the wire version that was read.
+    UnknownWireVersion(UnknownWireVersion),
+    /// The `using` function for the matching range returned an error.
+    Read(E),
+}
+
+impl<E: std::fmt::Display> std::fmt::Display for VersionedTypeError<E> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            VersionedTypeError::UnknownWireVersion(e) => e.fmt(f),
+            VersionedTypeError::Read(e) => e.fmt(f),
+        }
+    }
+}
+
+impl<E: std::fmt::Debug + std::fmt::Display> std::error::Error for VersionedTypeError<E> {}
+
+#[cfg(test)]
+mod tests {
+    use crate::{FromVersion, Versioned};
+    use std::io::Read;
+
+    #[derive(Debug)]
+    struct FooV1 {
+        val: u32,
+    }
+    #[derive(Debug)]
+    struct FooV2 {
+        val: u32,
+    }
+
+    impl Versioned for FooV1 {
+        const VER: u16 = 1;
+    }
+    impl Versioned for FooV2 {
+        const VER: u16 = 2;
+    }
+    impl FromVersion<FooV1> for FooV2 {
+        fn from_version(v: FooV1) -> Self {
+            FooV2 { val: v.val }
+        }
+    }
+
+    fn read_v1<R: Read>(_r: &mut R) -> std::io::Result<FooV1> {
+        Ok(FooV1 { val: 1 })
+    }
+    fn read_v2<R: Read>(_r: &mut R) -> std::io::Result<FooV2> {
+        Ok(FooV2 { val: 2 })
+    }
+
+    // Arms are declared ascending here, the opposite of the module doc
+    // example, to exercise that arm order doesn't matter.
+    crate::versioned_type! {
+        latest = FooV2,
+        error = std::io::Error,
+        fn_name = upgrade_foo,
+        1.. => FooV1, using read_v1,
+        2.. => FooV2, using read_v2,
+    }
+
+    #[test]
+    fn picks_lowest_range_below_its_upper_neighbor() {
+        let mut cursor = std::io::Cursor::new(Vec::<u8>::new());
+        let v = upgrade_foo(&mut cursor, 1).unwrap();
+        assert_eq!(v.val, 1);
+    }
+
+    #[test]
+    fn out_of_order_arms_still_pick_highest_matching_min() {
+        let mut cursor = std::io::Cursor::new(Vec::<u8>::new());
+        // Arms above are declared ascending (1.. before 2..); wire
+        // version 2 must still resolve to FooV2, not fall through to
+        // the first-declared FooV1 arm.
+        let v = upgrade_foo(&mut cursor, 2).unwrap();
+        assert_eq!(v.val, 2);
+    }
+
+    #[test]
+    fn unknown_wire_version_errors() {
+        let mut cursor = std::io::Cursor::new(Vec::<u8>::new());
+        let err = upgrade_foo(&mut cursor, 0).unwrap_err();
+        assert!(matches!(
+            err,
+            super::VersionedTypeError::UnknownWireVersion(super::UnknownWireVersion {
+                wire_version: 0
+            })
+        ));
+    }
+}